@@ -0,0 +1,339 @@
+/**
+
+    The transport-agnostic bridge.
+
+    `Handler::message` used to hard-code "source channel -> aggregate
+    channel" directly: translate, then post. This module pulls that flow
+    apart into pieces that don't assume Discord is the only transport, the
+    way phoebe/abridged bridge between services:
+
+        - a `ChatMessageReference` identifies a message on some transport
+          (`service`, `channel_id`, `message_id`) without Discord-specific
+          types leaking out;
+        - a `Linkmap` maps a logical "room" to the concrete channels (across
+          transports) that are bridged together;
+        - a `BridgeMessage` is what an inbound task normalizes a received
+          message into before it's translated;
+        - the dispatcher is a single long-lived task: it reads
+          `BridgeMessage`s off an inbound `tokio::mpsc` channel, translates
+          the content per each destination's configured language (DeepL as
+          a middleware step, not inlined here), and hands the result to the
+          destination transport's outbound channel - skipping the channel
+          the message originated from so we don't echo it straight back. A
+          DeepL error for one message is logged and that one delivery is
+          dropped (see `spawn_dispatcher` below) rather than taking the
+          whole dispatcher down, but there's no actual supervisor here -
+          nothing restarts the task if it panics for some other reason.
+
+    Only Discord exists as a transport today, so there's one outbound
+    channel. Adding Matrix/IRC later means adding another `Service` variant,
+    another outbound channel (and its arm in `spawn_dispatcher`'s delivery
+    `match`), and another inbound task that feeds the same dispatcher.
+
+*/
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serenity::prelude::{RwLock, TypeMapKey};
+use tokio::sync::mpsc;
+
+use crate::deepl::translate_message;
+use crate::runtime_config::ChannelConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Service {
+    Discord,
+}
+
+// A concrete channel on some transport, and the language messages posted
+// into it should be translated to. Equality only considers the
+// channel identity (service + channel_id), not the configured language, so
+// a `Linkmap` lookup doesn't need to know a channel's language up front.
+#[derive(Debug, Clone)]
+pub struct ChannelReference {
+    pub service: Service,
+    pub channel_id: String,
+    pub language: String,
+}
+
+impl PartialEq for ChannelReference {
+    fn eq(&self, other: &Self) -> bool {
+        self.service == other.service && self.channel_id == other.channel_id
+    }
+}
+impl Eq for ChannelReference {}
+
+// A specific message on some transport, used to identify where a message
+// came from (and, in future, what it's replying to across transports).
+#[derive(Debug, Clone)]
+pub struct ChatMessageReference {
+    pub service: Service,
+    pub channel_id: String,
+    pub message_id: String,
+}
+
+// A file attached to a message, carried by URL until the destination
+// transport re-hosts it (Discord re-uploads the bytes - see
+// `webhook::post_translation`).
+#[derive(Debug, Clone)]
+pub struct AttachmentRef {
+    pub url: String,
+    pub filename: String,
+}
+
+// The subset of a Discord embed's fields worth carrying across transports -
+// enough to preserve a link preview or an image embed, not a faithful
+// reproduction of every field `serenity::model::channel::Embed` exposes
+// (provider, video, the per-field `inline` flag, etc.), the same trade-off
+// `AttachmentRef` makes for attachments.
+#[derive(Debug, Clone, Default)]
+pub struct EmbedRef {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub url: Option<String>,
+    pub color: Option<u32>,
+    pub image_url: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+// A message received from a transport, normalized before translation.
+pub struct BridgeMessage {
+    pub origin: ChatMessageReference,
+    // The transport-native id of whoever wrote `content`, carried through
+    // so the posted translation's `TranslationRecord` can be looked up by
+    // `language.rs` later - see `OutboundMessage::author_id` below.
+    pub author_id: String,
+    pub author_name: String,
+    pub author_avatar_url: String,
+    pub content: String,
+    pub attachments: Vec<AttachmentRef>,
+    pub embeds: Vec<EmbedRef>,
+}
+
+// A translated message ready to deliver to one destination channel.
+pub struct OutboundMessage {
+    pub target: ChannelReference,
+    pub text: String,
+    pub detected_source_language: String,
+    // See `BridgeMessage::author_id`. A room can have more than one member
+    // reading it, so there's no single recipient to resolve a
+    // `language.rs` preference against here - that only happens once a
+    // reply addresses a specific message back to this author.
+    pub author_id: String,
+    pub author_name: String,
+    pub author_avatar_url: String,
+    pub origin: ChatMessageReference,
+    pub attachments: Vec<AttachmentRef>,
+    pub embeds: Vec<EmbedRef>,
+}
+
+pub type RoomId = String;
+
+// Maps a logical "room" to the concrete channels (across transports) that
+// are bridged together. A message arriving on one member of a room is
+// translated and delivered to every other member.
+#[derive(Default, Clone)]
+pub struct Linkmap {
+    rooms: HashMap<RoomId, Vec<ChannelReference>>,
+}
+
+impl Linkmap {
+    pub fn insert(&mut self, room: RoomId, members: Vec<ChannelReference>) {
+        self.rooms.insert(room, members);
+    }
+
+    // Every other member of every room `origin` belongs to. Comparing by
+    // channel identity (not by value) means `origin`'s own entry is
+    // filtered out even though it carries a different `language` to the
+    // stored member - we don't want to echo a message back to where it
+    // came from.
+    pub fn targets_for(&self, origin: &ChannelReference) -> Vec<ChannelReference> {
+        self.rooms
+            .values()
+            .filter(|members| members.contains(origin))
+            .flat_map(|members| members.iter().cloned())
+            .filter(|member| member != origin)
+            .collect()
+    }
+}
+
+pub struct LinkmapKey;
+impl TypeMapKey for LinkmapKey {
+    type Value = Arc<RwLock<Linkmap>>;
+}
+
+// Build the Linkmap from the current channel config: one room per source
+// channel, bridging it to the aggregate channel. This preserves the
+// original hub-and-spoke behaviour (source channels don't see each other)
+// while expressing it through the generic room model, so it can grow into
+// genuine multi-way rooms later without another rewrite.
+pub fn build_linkmap(channel_config: &ChannelConfig, default_language: &str) -> Linkmap {
+    let mut linkmap = Linkmap::default();
+
+    let aggregate = ChannelReference {
+        service: Service::Discord,
+        channel_id: channel_config.aggregate_channel_id.0.to_string(),
+        language: default_language.to_string(),
+    };
+
+    for (channel_id, language) in &channel_config.source_channel_language {
+        let source = ChannelReference {
+            service: Service::Discord,
+            channel_id: channel_id.0.to_string(),
+            language: language.clone(),
+        };
+
+        linkmap.insert(format!("source:{}", channel_id.0), vec![source, aggregate.clone()]);
+    }
+
+    linkmap
+}
+
+// The inbound side of the bridge: transports hand normalized messages to
+// the dispatcher over this channel.
+pub struct BridgeInbound;
+impl TypeMapKey for BridgeInbound {
+    type Value = mpsc::Sender<BridgeMessage>;
+}
+
+// The outbound side for the Discord transport: the dispatcher hands
+// translated messages to Discord's delivery worker over this channel.
+pub struct DiscordOutbound;
+impl TypeMapKey for DiscordOutbound {
+    type Value = mpsc::Sender<OutboundMessage>;
+}
+
+// The Discord delivery worker needs a live `Context` (for the webhook
+// cache and HTTP client), which only exists once the client has connected,
+// so the receiving half of the outbound channel is handed out once, inside
+// `ready`, rather than spawning the worker directly in `main`.
+pub struct DiscordOutboundReceiver;
+impl TypeMapKey for DiscordOutboundReceiver {
+    type Value = Arc<tokio::sync::Mutex<Option<mpsc::Receiver<OutboundMessage>>>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: &str, language: &str) -> ChannelReference {
+        ChannelReference { service: Service::Discord, channel_id: id.to_string(), language: language.to_string() }
+    }
+
+    #[test]
+    fn targets_for_returns_other_room_members_but_not_the_origin() {
+        let mut linkmap = Linkmap::default();
+        linkmap.insert("source:1".to_string(), vec![channel("1", "en"), channel("2", "fr")]);
+
+        let targets = linkmap.targets_for(&channel("1", "en"));
+
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].channel_id, "2");
+    }
+
+    #[test]
+    fn targets_for_matches_origin_by_channel_identity_not_language() {
+        // The stored member's language differs from what the caller passes
+        // as `origin` - the two should still be recognised as the same
+        // channel and excluded from the result.
+        let mut linkmap = Linkmap::default();
+        linkmap.insert("source:1".to_string(), vec![channel("1", "en"), channel("2", "fr")]);
+
+        let targets = linkmap.targets_for(&channel("1", ""));
+
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].channel_id, "2");
+    }
+
+    #[test]
+    fn targets_for_a_channel_in_no_room_is_empty() {
+        let linkmap = Linkmap::default();
+        assert!(linkmap.targets_for(&channel("1", "en")).is_empty());
+    }
+
+    #[test]
+    fn targets_for_collects_across_every_room_the_origin_belongs_to() {
+        let mut linkmap = Linkmap::default();
+        linkmap.insert("source:1".to_string(), vec![channel("1", "en"), channel("2", "fr")]);
+        linkmap.insert("source:1-extra".to_string(), vec![channel("1", "en"), channel("3", "de")]);
+
+        let mut targets: Vec<String> = linkmap.targets_for(&channel("1", "en")).into_iter().map(|c| c.channel_id).collect();
+        targets.sort();
+
+        assert_eq!(targets, vec!["2".to_string(), "3".to_string()]);
+    }
+}
+
+// Translates each inbound `BridgeMessage` via DeepL and forwards the result
+// to every other member of its room, over that member's transport's
+// outbound channel. Runs as a single long-lived task, not a supervised one
+// - nothing restarts it if it dies. A DeepL failure for one message is
+// logged and that one delivery is skipped (see the `match` below) so it
+// can't take the rest of the bridge down with it.
+pub fn spawn_dispatcher(
+    mut inbound_rx: mpsc::Receiver<BridgeMessage>,
+    linkmap: Arc<RwLock<Linkmap>>,
+    deepl_api_key: String,
+    discord_outbound_tx: mpsc::Sender<OutboundMessage>,
+) {
+    tokio::spawn(async move {
+        while let Some(message) = inbound_rx.recv().await {
+            let origin_channel = ChannelReference {
+                service: message.origin.service,
+                channel_id: message.origin.channel_id.clone(),
+                language: String::new(),
+            };
+
+            let targets = linkmap.read().await.targets_for(&origin_channel);
+
+            for target in targets {
+                // A room can bridge a channel read by many people - the
+                // aggregate channel being the obvious case - so there's no
+                // single recipient here whose `language.rs` preference
+                // could apply; a shared channel can only carry one
+                // translation, and this one is always the destination
+                // channel's configured language (which itself already
+                // falls back to `config.default_language` - see
+                // `build_linkmap`). That means an admin's preference is
+                // never consulted for the forwarded broadcast itself, only
+                // once a reply is addressed back to one specific author -
+                // see `Handler::message`'s reply branch in `main.rs`. This
+                // is a deliberate limitation, not an oversight: there's no
+                // way to honour every admin's preference with one posted
+                // message.
+                let translation = match translate_message(
+                    message.content.clone(),
+                    target.language.clone(),
+                    &deepl_api_key,
+                ).await {
+                    Ok(translation) => translation,
+                    Err(why) => {
+                        println!("Error translating bridge message via DeepL, dropping this delivery: {}", why);
+                        continue;
+                    }
+                };
+
+                let outbound = OutboundMessage {
+                    target: target.clone(),
+                    text: translation.text,
+                    detected_source_language: translation.detected_source_language,
+                    author_id: message.author_id.clone(),
+                    author_name: message.author_name.clone(),
+                    author_avatar_url: message.author_avatar_url.clone(),
+                    origin: message.origin.clone(),
+                    attachments: message.attachments.clone(),
+                    embeds: message.embeds.clone(),
+                };
+
+                let delivery = match target.service {
+                    Service::Discord => discord_outbound_tx.send(outbound).await,
+                };
+
+                if let Err(why) = delivery {
+                    println!("Error forwarding bridge message to its destination transport: {:?}", why);
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,122 @@
+/**
+
+    Admin commands for changing the bot's runtime config without editing
+    `Settings` and restarting, modeled on the mpsc_bot command set
+    (`set source/target`, `reset`, `stop`, `begin`).
+
+*/
+
+use serenity::model::id::ChannelId;
+
+pub const COMMAND_PREFIX: &str = "!babelfish ";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    SetSource { channel_id: ChannelId, language: String },
+    SetAggregate { channel_id: ChannelId },
+    RemoveSource { channel_id: ChannelId },
+    Reset,
+    // Unlike the rest of `Command`, this is a per-user preference rather
+    // than channel config, so `handle_command` doesn't gate it behind
+    // manage-channels - see `language.rs`.
+    SetLanguage { language: String },
+}
+
+// Parse a config command out of a message's content, e.g.
+// "!babelfish set source <#123456789012345678> en" or "!babelfish reset". Returns None
+// for anything that isn't a recognised command, including ordinary
+// messages, so callers can fall through to the usual translation flow.
+pub fn parse_command(content: &str) -> Option<Command> {
+    let rest = content.trim().strip_prefix(COMMAND_PREFIX)?;
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+
+    match parts.as_slice() {
+        ["set", "source", channel_id, language] => Some(Command::SetSource {
+            channel_id: parse_channel_id(channel_id)?,
+            language: language.to_string(),
+        }),
+        ["set", "aggregate", channel_id] => Some(Command::SetAggregate {
+            channel_id: parse_channel_id(channel_id)?,
+        }),
+        ["remove", "source", channel_id] => Some(Command::RemoveSource {
+            channel_id: parse_channel_id(channel_id)?,
+        }),
+        ["set", "language", language] => Some(Command::SetLanguage {
+            language: language.to_string(),
+        }),
+        ["reset"] => Some(Command::Reset),
+        _ => None,
+    }
+}
+
+// Accepts either a raw snowflake or a `<#channel_id>` mention, since that's
+// what Discord clients actually insert when you type `#channel-name`.
+fn parse_channel_id(raw: &str) -> Option<ChannelId> {
+    let trimmed = raw.trim_start_matches("<#").trim_end_matches('>');
+    trimmed.parse::<u64>().ok().map(ChannelId::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_set_source_with_a_raw_snowflake() {
+        assert_eq!(
+            parse_command("!babelfish set source 123456789012345678 en"),
+            Some(Command::SetSource { channel_id: ChannelId::from(123456789012345678), language: "en".to_string() })
+        );
+    }
+
+    #[test]
+    fn parses_set_source_with_a_channel_mention() {
+        assert_eq!(
+            parse_command("!babelfish set source <#123> fr"),
+            Some(Command::SetSource { channel_id: ChannelId::from(123), language: "fr".to_string() })
+        );
+    }
+
+    #[test]
+    fn parses_set_aggregate() {
+        assert_eq!(
+            parse_command("!babelfish set aggregate <#456>"),
+            Some(Command::SetAggregate { channel_id: ChannelId::from(456) })
+        );
+    }
+
+    #[test]
+    fn parses_remove_source() {
+        assert_eq!(
+            parse_command("!babelfish remove source <#456>"),
+            Some(Command::RemoveSource { channel_id: ChannelId::from(456) })
+        );
+    }
+
+    #[test]
+    fn parses_set_language() {
+        assert_eq!(
+            parse_command("!babelfish set language de"),
+            Some(Command::SetLanguage { language: "de".to_string() })
+        );
+    }
+
+    #[test]
+    fn parses_reset() {
+        assert_eq!(parse_command("!babelfish reset"), Some(Command::Reset));
+    }
+
+    #[test]
+    fn ignores_ordinary_messages() {
+        assert_eq!(parse_command("just chatting about the weather"), None);
+    }
+
+    #[test]
+    fn rejects_an_unrecognised_subcommand() {
+        assert_eq!(parse_command("!babelfish frobnicate"), None);
+    }
+
+    #[test]
+    fn rejects_a_malformed_channel_id() {
+        assert_eq!(parse_command("!babelfish set aggregate not-a-channel"), None);
+    }
+}
@@ -0,0 +1,94 @@
+/**
+
+    The DeepL translation middleware.
+
+    This used to be inlined directly in `Handler::message`; it's now a
+    standalone step the bridge dispatcher (`bridge.rs`) calls between
+    receiving a message and delivering it, so translation isn't tied to
+    Discord specifically.
+
+*/
+
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::from_str;
+
+use crate::markdown::{splice_non_translatable, strip_non_translatable};
+
+// DeepL returns a Vec<Translation>, so we deserialise through two types, a
+// container (DeepLResponse) and an individual item (Translation)
+#[derive(Deserialize, Debug, Clone)]
+pub struct Translation {
+    pub text: String,
+    pub detected_source_language: String
+}
+#[derive(Deserialize, Debug, Clone)]
+struct DeepLResponse {
+    translations: Vec<Translation>
+}
+
+// Everything that can go wrong talking to DeepL: the request itself
+// (timeout, connection refused), DeepL handing back something that isn't
+// the JSON shape we expect (a rate-limit/quota page, an HTML error body),
+// or a well-formed response with no translations in it. Callers decide
+// what "couldn't translate this one message" should mean for them -
+// `spawn_dispatcher` drops just that delivery and keeps going, rather than
+// a bad DeepL response taking down the whole bridge.
+#[derive(Debug)]
+pub enum DeepLError {
+    Request(reqwest::Error),
+    UnexpectedResponse(String),
+}
+
+impl fmt::Display for DeepLError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DeepLError::Request(why) => write!(f, "DeepL request failed: {:?}", why),
+            DeepLError::UnexpectedResponse(body) => write!(f, "DeepL returned an unexpected response: {}", body),
+        }
+    }
+}
+
+// Actually do the translation HTTP request to DeepL
+pub async fn translate_message(msg: String, language_code: String, api_key: &String) -> Result<Translation, DeepLError> {
+
+    // Code blocks, Discord mentions/emoji, and bare URLs aren't prose DeepL
+    // should be translating - strip them out before the request and splice
+    // the originals back into whatever DeepL hands back.
+    let stripped = strip_non_translatable(&msg);
+
+    // Construct the body of the request
+    let form_data = [("text", stripped.text.clone()), ("target_lang", language_code.clone())];
+
+    let response = reqwest::Client::new()
+        .post(format!("https://api-free.deepl.com/v2/translate?auth_key={}", api_key)) // <- Create request builder
+        .header("User-Agent", "Actix-web")
+        .form(&form_data)
+        .send()
+        .await
+        .map_err(DeepLError::Request)?
+        .text()
+        .await
+        .map_err(DeepLError::Request)?;
+
+    // DeepL gives us back a vector of possible translations, depending on the
+    // language that it thinks the message is written in. We only care about
+    // returning the first one.
+    println!("Posted message \"{}\" to DeepL with target language {} and got back {}", msg.clone(), language_code.clone(), &response.clone());
+    let translated_message: DeepLResponse = from_str(&response)
+        .map_err(|_| DeepLError::UnexpectedResponse(response.clone()))?;
+    let first_translation = translated_message
+        .translations
+        .first()
+        .ok_or_else(|| DeepLError::UnexpectedResponse(response.clone()))?;
+    if first_translation.detected_source_language == language_code.clone() {
+        return Ok(Translation { text: String::from(""), detected_source_language: language_code })
+    }
+
+    Ok(Translation {
+        text: splice_non_translatable(&first_translation.text, &stripped),
+        detected_source_language: first_translation.detected_source_language.clone(),
+    })
+
+}
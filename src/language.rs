@@ -0,0 +1,86 @@
+/**
+
+    Per-user target-language preferences.
+
+    Lets an individual user register a preferred language (`!babelfish set
+    language <code>`). A message forwarded by the bridge (`bridge.rs`) is a
+    broadcast to every member of a room, so there's no single reader to
+    apply a preference against there - it's only consulted once a reply is
+    addressed back to one specific message, in `Handler::message`'s reply
+    branch in `main.rs`. There, the preference of the message's original
+    author overrides the stored language it would otherwise be translated
+    back into. Resolution order is: explicit per-user preference -> the
+    stored/destination language, which itself already falls back to
+    `config.default_language` - see `bridge::build_linkmap`.
+
+    This is a deliberate, known limitation, not an oversight: a shared
+    channel (the aggregate channel is the obvious case) can only carry one
+    posted translation, so there's no way for the *forwarded* broadcast
+    itself to honour several admins' differing preferences at once -
+    preferences only ever apply to the single addressed reply going back
+    to one specific person.
+
+*/
+
+use std::sync::Arc;
+
+use serenity::model::id::UserId;
+use serenity::prelude::TypeMapKey;
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+// The subset of DeepL's supported target languages we validate preferences
+// against. https://www.deepl.com/docs-api/translating-text/request/
+const SUPPORTED_LANGUAGES: &[&str] = &[
+    "BG", "CS", "DA", "DE", "EL", "EN", "ES", "ET", "FI", "FR", "HU", "ID", "IT", "JA", "LT", "LV",
+    "NL", "PL", "PT", "RO", "RU", "SK", "SL", "SV", "TR", "UK", "ZH",
+];
+
+pub fn is_supported_language(code: &str) -> bool {
+    SUPPORTED_LANGUAGES.contains(&code.to_uppercase().as_str())
+}
+
+pub struct LanguageManager {
+    pool: SqlitePool,
+}
+
+impl TypeMapKey for LanguageManager {
+    type Value = Arc<LanguageManager>;
+}
+
+impl LanguageManager {
+    // Shares the translation store's connection pool, same as `ConfigStore`.
+    pub async fn connect(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS user_language_preferences (
+                user_id INTEGER PRIMARY KEY,
+                language TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    pub async fn language_of(&self, user_id: UserId) -> Option<String> {
+        sqlx::query("SELECT language FROM user_language_preferences WHERE user_id = ?")
+            .bind(user_id.0 as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .ok()?
+            .map(|row| row.get("language"))
+    }
+
+    pub async fn set_language(&self, user_id: UserId, language: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO user_language_preferences (user_id, language) VALUES (?, ?)",
+        )
+        .bind(user_id.0 as i64)
+        .bind(language.to_uppercase())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
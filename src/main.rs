@@ -43,53 +43,65 @@ use std::{collections::HashMap, sync::Arc};
 
 // Useful http things
 use serde::Deserialize;
-use serde_json::{from_str};
-use reqwest;
 
 // Discord Client
-use serenity::{async_trait, client::{Context, EventHandler, ClientBuilder}, model::{channel::{Message, MessageReference}, gateway::Ready, id::{MessageId, ChannelId, UserId}}, prelude::{RwLock,TypeMapKey}};
-
-// DeepL returns a Vec<Translation>, so we deserialise through two types, a
-// container (DeepLResponse) and an individual item (Translation)
-#[derive(Deserialize, Debug, Clone)]
-pub struct Translation {
-    text: String,
-    detected_source_language: String
-}
-#[derive(Deserialize, Debug, Clone)]
-struct DeepLResponse {
-    translations: Vec<Translation>
-}
-#[derive(Deserialize, Debug, Clone)]
-struct PastTranslation {
-    channel_id: ChannelId,
-    message_id: MessageId,
-    language: String
-}
+use serenity::{async_trait, client::{Context, EventHandler, ClientBuilder}, model::{channel::Message, event::MessageUpdateEvent, gateway::Ready, id::{GuildId, MessageId, ChannelId, UserId}}, prelude::{RwLock, TypeMapKey}};
+
+mod store;
+use store::{TranslationRecord, TranslationStore};
+
+mod webhook;
+use webhook::{delete_translation, edit_translation, post_translation, WebhookCache};
+
+mod runtime_config;
+use runtime_config::{ChannelConfig, ConfigStore, RuntimeConfig};
+
+mod commands;
+use commands::{parse_command, Command};
+
+mod deepl;
+use deepl::translate_message;
+
+mod bridge;
+use bridge::{AttachmentRef, BridgeInbound, BridgeMessage, ChannelReference, ChatMessageReference, EmbedRef, OutboundMessage, Service, DiscordOutbound, DiscordOutboundReceiver, LinkmapKey};
+
+mod markdown;
+
+mod language;
+use language::{is_supported_language, LanguageManager};
+
+use tokio::sync::mpsc;
+
+use serenity::model::permissions::Permissions;
+
 struct BotMessage {
     target_channel_id: ChannelId,
     target_language: String,
     target_reply_to_message: MessageId
 }
 
-// A map of MessageId => String
+// The translation memory, keyed by MessageId => TranslationRecord
 struct Translations;
 
-// The thing we're storing is a rw-locked HashMap. wrapped in an Arc for thread
-// safety
+// This used to be an Arc<RwLock<HashMap<..>>> held in memory; it's now a
+// handle to the sqlite-backed store, so mappings between source and
+// aggregate messages survive a restart.
 impl TypeMapKey for Translations {
-    type Value = Arc<RwLock<HashMap<MessageId, PastTranslation>>>;
+    type Value = Arc<TranslationStore>;
 }
 
 // I would like this to be a config struct I guess?
+// `aggregate_channel_id` and `source_channel_language` used to live here,
+// but they now change at runtime via admin commands, so they live in
+// `runtime_config::ChannelConfig` instead, behind an `Arc<RwLock<..>>` and
+// persisted to the database.
 #[derive(Deserialize, Debug, Default, Clone)]
 struct AppConfig {
     bot_token: String,
     bot_user_id: UserId,
     deepl_api_key: String,
-    aggregate_channel_id: ChannelId,
-    source_channel_language: HashMap<ChannelId, String>,
     default_language: String,
+    database_url: String,
 }
 
 impl TypeMapKey for AppConfig {
@@ -117,12 +129,46 @@ impl EventHandler for Handler {
         };
 
         println!("Got message from {:?} ({}): {}", msg.author.id, msg.author.name, msg.content.clone());
+
+        // Translations are posted through a per-channel webhook (see
+        // `webhook.rs`), not as the bot user, so `is_bot_message` below
+        // can't catch them - a webhook message's author is the webhook
+        // itself, not `config.bot_user_id`. Without this guard a
+        // back-translation posted into a source channel would re-enter
+        // here as a fresh source message, get re-forwarded, and its
+        // `INSERT OR REPLACE` would overwrite the original
+        // `is_bot_message=true` record with a `false` one, corrupting
+        // reply routing.
+        if msg.webhook_id.is_some() {
+            println!("This is a webhook message, ignoring.");
+            return
+        }
+
         // Don't care about messages from self.
         if is_bot_message(config.bot_user_id, msg.author.id) {
             println!("This is the bot, ignoring.");
             return
         }
-        if !is_monitored_channel(config.aggregate_channel_id, config.source_channel_language.clone(), msg.channel_id.clone()) {
+
+        // Admin commands (`!babelfish set source ...`) are handled
+        // separately from the translation flow and never fall through to it.
+        if let Some(command) = parse_command(&msg.content) {
+            handle_command(&ctx, &msg, command).await;
+            return;
+        }
+
+        // channel_config holds the mutable parts of the config
+        // (aggregate_channel_id, source_channel_language), which can be
+        // changed at runtime by the commands above.
+        let channel_config = {
+            let runtime_config = {
+                let data = ctx.data.read().await;
+                data.get::<RuntimeConfig>().expect("Expected RuntimeConfig").clone()
+            };
+            runtime_config.read().await.clone()
+        };
+
+        if !is_monitored_channel(channel_config.aggregate_channel_id, channel_config.source_channel_language.clone(), msg.channel_id.clone()) {
             println!("This message is in a non-configured channel, ignoring");
             return
         }
@@ -136,7 +182,7 @@ impl EventHandler for Handler {
         // If we are posting in the aggregate channel and we are not replying
         // to anyone, we don't want to make a babelfish message
 
-        if msg.channel_id == config.aggregate_channel_id && reply_to.message_id == 0 {
+        if msg.channel_id == channel_config.aggregate_channel_id && reply_to.message_id == 0 {
             println!("This message in the aggregate channel is not a reply, ignoring.");
             return;
         };
@@ -144,36 +190,93 @@ impl EventHandler for Handler {
         // If we have the channel id in the config map, we should get the
         // source language here
         let channel_lang;
-        if config.source_channel_language.contains_key(&msg.channel_id) {
-            channel_lang = String::from(config.source_channel_language.get(&msg.channel_id).unwrap());
+        if channel_config.source_channel_language.contains_key(&msg.channel_id) {
+            channel_lang = String::from(channel_config.source_channel_language.get(&msg.channel_id).unwrap());
             println!("Found message in channel {:?} with expected source language {}", msg.channel_id, channel_lang);
-        } else if msg.channel_id != config.aggregate_channel_id {
+        } else if msg.channel_id != channel_config.aggregate_channel_id {
             println!("The bot is not active in the channel with ID: {}", msg.channel_id);
             return;
         } else {
             channel_lang = config.default_language.clone();
         }
 
-        // Get a the thread-safe lock on the translations from the context's data store
-        let translations_lock = {
+        // Get a handle on the translation store from the context's data map.
+        let translation_store = {
             // We need to read the data first, so let's do that for now.
             // Careless use of write locks could cause our program to lock.
             let data_read = ctx.data.read().await;
 
-            // Cloning the value will not duplicate the data, just the reference
-            // Wrapping the value in Arc means we can keep the data lock open
-            // for minimal time
+            // Cloning the value will not duplicate the data, just the Arc
+            // pointing at the shared sqlite pool, so we can close the data
+            // lock as soon as possible.
             data_read.get::<Translations>().expect("Expected something").clone()
         };
 
-        let default_language = &config.default_language;
+        let past_translation = TranslationRecord {
+            language: channel_lang.clone(),
+            channel_id: msg.channel_id,
+            message_id: msg.id,
+            source_message_id: msg.id,
+            is_bot_message: false,
+            posted_channel_id: msg.channel_id,
+            author_id: msg.author.id
+        };
+
+        // Now write this message's id to storage, keying its source language
+        if let Err(why) = translation_store.insert(&past_translation).await {
+            println!("Error storing translation record: {:?}", why);
+        } else {
+            println!("Stored the message {:?} with key {}", past_translation, msg.id.clone());
+        }
+
+        let from_name = match msg.clone().author_nick(&ctx.http).await {
+            Some(_nickname) => _nickname,
+            None => msg.clone().author.name
+        };
+
+        if reply_to.message_id == 0 {
+            // The plain case: this is a new message, not a reply to
+            // anything. This is the flow that used to be hard-coded as
+            // "source channel -> aggregate channel"; it's now handed to the
+            // bridge dispatcher, which resolves delivery targets (and their
+            // languages) via the Linkmap instead.
+            let bridge_tx = {
+                let data = ctx.data.read().await;
+                data.get::<BridgeInbound>().expect("Expected BridgeInbound").clone()
+            };
+
+            let bridge_message = BridgeMessage {
+                origin: ChatMessageReference {
+                    service: Service::Discord,
+                    channel_id: msg.channel_id.0.to_string(),
+                    message_id: msg.id.0.to_string()
+                },
+                author_id: msg.author.id.0.to_string(),
+                author_name: from_name,
+                author_avatar_url: msg.author.face(),
+                content: msg.content.clone(),
+                attachments: message_attachments(&msg),
+                embeds: message_embeds(&msg)
+            };
+
+            if let Err(why) = bridge_tx.send(bridge_message).await {
+                println!("Error handing message to the bridge dispatcher: {:?}", why);
+            }
+
+            return;
+        }
+
+        // The reply-threading case: this resolves one precise target via
+        // the translation store below (or falls back to the default
+        // aggregate channel), so it's delivered directly instead of going
+        // through the Linkmap broadcast above.
 
         // Unless we discern otherwise, a message in this channel should be
         // translated into the operator_language and result in an Aggregate Bot
         // Message.
         let mut target_message = BotMessage {
-            target_channel_id: ChannelId::from(config.aggregate_channel_id),
-            target_language: String::from(default_language),
+            target_channel_id: ChannelId::from(channel_config.aggregate_channel_id),
+            target_language: config.default_language.clone(),
             // A reply to the BotMessage should result in the _next_ BotMessage
             // replying to the original message!
             target_reply_to_message: MessageId::from(msg.id)
@@ -196,28 +299,34 @@ impl EventHandler for Handler {
 
         // Now we need to find out if the replied-to message has been translated
         // already. If it has, we'll translate back to its source language.
-        // To do this, we need to activate our read lock on the data, then use
-        // it to overwrite the default target language which was derived from
-        // the channel name
-        {
-            let all_past_translations = translations_lock
-                .read()
-                .await;
-
-            let referenced_past_translation = all_past_translations
-                .get(&reply_to.message_id);
-
-            target_message = match referenced_past_translation {
-                Some(s) => BotMessage {
+        let referenced_past_translation = translation_store.get(reply_to.message_id).await;
+
+        target_message = match referenced_past_translation {
+            Some(s) => {
+                // Unlike the Linkmap broadcast above, this reply is
+                // addressed to one specific message, so there's exactly one
+                // recipient - `s.author_id`, the person who wrote it. Their
+                // own `language.rs` preference, if they've set one, takes
+                // priority over the stored language.
+                let language_manager = {
+                    let data = ctx.data.read().await;
+                    data.get::<LanguageManager>().expect("Expected LanguageManager").clone()
+                };
+                let target_language = language_manager
+                    .language_of(s.author_id)
+                    .await
+                    .unwrap_or_else(|| s.language.clone());
+
+                BotMessage {
                     // The target channel ID is the inverse.
                     // Source -> Aggregate
                     // Aggregate -> Source
                     target_channel_id: s.channel_id,
-                    target_language: s.language.clone(),
+                    target_language,
                     target_reply_to_message: s.message_id
-                },
-                None => target_message
-            };
+                }
+            },
+            None => target_message
         };
 
         // If the message we are replying to was _not_ written by the bot, we
@@ -227,12 +336,12 @@ impl EventHandler for Handler {
                 if
                     replying_to.id != 0 &&
                     replying_to.author.id != config.bot_user_id &&
-                    msg.channel_id != config.aggregate_channel_id
+                    msg.channel_id != channel_config.aggregate_channel_id
                 {
                     // ideally here we would be replying to the original translation
                     // in the aggregate_channel, but that's just going to melt my brain
                     target_message.target_reply_to_message = MessageId::from(0);
-                    target_message.target_channel_id = config.aggregate_channel_id;
+                    target_message.target_channel_id = channel_config.aggregate_channel_id;
                     target_message.target_language = config.default_language.clone()
                 }
             },
@@ -242,110 +351,387 @@ impl EventHandler for Handler {
         println!("Translating to {}, then sending a message to channel {}", &target_message.target_language, target_message.target_channel_id);
 
         // Go do the translation with deepL
-        let translation = translate_message(
+        let translation = match translate_message(
             msg.content.clone(),
             String::from(&target_message.target_language),
             &config.deepl_api_key
-        ).await;
+        ).await {
+            Ok(translation) => translation,
+            Err(why) => {
+                println!("Error translating reply via DeepL, dropping it: {}", why);
+                return;
+            }
+        };
 
-        let past_translation = PastTranslation {
-            language: channel_lang.clone(),
-            channel_id: msg.channel_id,
-            message_id: msg.id
+        // If `msg` is a reply TO A BOT MESSAGE, log which message we'd have
+        // threaded onto. Webhook messages can't natively reply, so the
+        // actual back-translation routing is done via the Translations
+        // store instead of an in-reply-to reference.
+        if is_bot_message(config.bot_user_id, reply_to.author_id) {
+            println!("This message is a reply to {} in the channel {}", target_message.target_reply_to_message, target_message.target_channel_id);
+        }
+
+        let discord_outbound_tx = {
+            let data = ctx.data.read().await;
+            data.get::<DiscordOutbound>().expect("Expected DiscordOutbound").clone()
         };
 
-        // Now write this message's id to storage, keying its source language
-        {
-            let mut translations = translations_lock.write().await;
-            translations.entry(msg.id.clone()).or_insert(past_translation.clone());
-            println!("Stored the message {:?} with key {}", past_translation.clone(), msg.id.clone());
+        let outbound = OutboundMessage {
+            target: ChannelReference {
+                service: Service::Discord,
+                channel_id: target_message.target_channel_id.0.to_string(),
+                language: target_message.target_language.clone()
+            },
+            text: translation.text,
+            detected_source_language: translation.detected_source_language,
+            author_id: msg.author.id.0.to_string(),
+            author_name: from_name,
+            author_avatar_url: msg.author.face(),
+            origin: ChatMessageReference {
+                service: Service::Discord,
+                channel_id: msg.channel_id.0.to_string(),
+                message_id: msg.id.0.to_string()
+            },
+            attachments: message_attachments(&msg),
+            embeds: message_embeds(&msg)
         };
 
-        let from_name = match msg.clone().author_nick(&ctx.http).await {
-            Some(_nickname) => _nickname,
-            None => msg.clone().author.name
+        if let Err(why) = discord_outbound_tx.send(outbound).await {
+            println!("Error delivering translated reply: {:?}", why);
+        }
+    }
+
+    async fn message_update(
+        &self,
+        ctx: Context,
+        _old_if_available: Option<Message>,
+        _new: Option<Message>,
+        event: MessageUpdateEvent,
+    ) {
+        let translation_store = {
+            let data = ctx.data.read().await;
+            data.get::<Translations>().expect("Expected something").clone()
         };
 
-        let sent_message_result = target_message.target_channel_id.send_message(&ctx.http, |f| {
+        // If this message isn't a source message we know about, there's
+        // nothing to propagate. This also guards against reacting to our
+        // own webhook edits below, since those are stored with
+        // is_bot_message = true.
+        let source_record = match translation_store.get(event.id).await {
+            Some(record) if !record.is_bot_message => record,
+            _ => return,
+        };
 
-            let content = format!("{} (from: {})", translation.text, from_name);
-            let mut message_builder = f.content(content);
+        let new_content = match &event.content {
+            Some(content) => content.clone(),
+            None => return,
+        };
 
-            // If `msg` is a reply TO A BOT MESSAGE, we want to attach the built
-            // message to something
-            if
-                reply_to.message_id != 0 &&
-                is_bot_message(config.bot_user_id, reply_to.author_id)
-            {
-                println!("This message is a reply to {} in the channel {}", target_message.target_reply_to_message, target_message.target_channel_id);
-                let msg_ref = MessageReference::from((target_message.target_channel_id, target_message.target_reply_to_message));
-                message_builder = message_builder.reference_message(msg_ref);
-            }
+        let target_record = match translation_store.get_by_source_message_id(source_record.message_id, true).await {
+            Some(record) => record,
+            None => return,
+        };
 
-            message_builder
-        }).await;
+        let config = {
+            let data = ctx.data.read().await;
+            data.get::<AppConfig>().expect("something").clone()
+        };
 
-        if let Err(why) = sent_message_result {
-            println!("Error sending message: {:?}", why);
-        } else {
-            let sent_message = sent_message_result.unwrap();
-            // We need to write this message to the translations map, so we know
-            // the language that we came from (and thus will know what language
-            // to return to).
-            {
-                let mut translations = translations_lock.write().await;
-                let translation = PastTranslation {
-                    language: translation.detected_source_language.clone(),
-                    channel_id: msg.channel_id,
-                    message_id: msg.id
-                };
-                translations.entry(sent_message.id.clone()).or_insert(translation.clone());
-                println!("Stored the message {:?} with key {}", translation.clone(), sent_message.id.clone());
+        // `target_record.language` is the *source* message's own detected
+        // language (stored so a reply can be translated back into it), not
+        // the language the destination channel expects - translating into
+        // it here would just hand DeepL back its own source language, which
+        // `translate_message` short-circuits to an empty string, blanking
+        // the aggregate translation on every edit. We need the destination
+        // channel's configured language instead, the same per-channel
+        // language `bridge::build_linkmap` assigns when the message is
+        // first forwarded.
+        let channel_config = {
+            let runtime_config = {
+                let data = ctx.data.read().await;
+                data.get::<RuntimeConfig>().expect("Expected RuntimeConfig").clone()
             };
+            runtime_config.read().await.clone()
+        };
+
+        let target_language = if target_record.posted_channel_id == channel_config.aggregate_channel_id {
+            config.default_language.clone()
+        } else {
+            channel_config
+                .source_channel_language
+                .get(&target_record.posted_channel_id)
+                .cloned()
+                .unwrap_or_else(|| config.default_language.clone())
+        };
+
+        let translation = match translate_message(new_content, target_language, &config.deepl_api_key).await {
+            Ok(translation) => translation,
+            Err(why) => {
+                println!("Error translating edited message via DeepL, leaving the old translation in place: {}", why);
+                return;
+            }
+        };
+
+        if let Err(why) = edit_translation(&ctx, target_record.posted_channel_id, target_record.message_id, &translation.text).await {
+            println!("Error editing translated message: {:?}", why);
         }
     }
 
-    async fn ready(&self, _: Context, ready: Ready) {
+    async fn message_delete(
+        &self,
+        ctx: Context,
+        _channel_id: ChannelId,
+        deleted_message_id: MessageId,
+        _guild_id: Option<GuildId>,
+    ) {
+        let translation_store = {
+            let data = ctx.data.read().await;
+            data.get::<Translations>().expect("Expected something").clone()
+        };
+
+        let record = match translation_store.get(deleted_message_id).await {
+            Some(record) => record,
+            None => return,
+        };
+
+        // If a source message was deleted, delete its translation too. If a
+        // translation itself was deleted, there's nothing further to
+        // propagate - just forget the mapping below.
+        if !record.is_bot_message {
+            if let Some(target_record) = translation_store.get_by_source_message_id(record.message_id, true).await {
+                if let Err(why) = delete_translation(&ctx, target_record.posted_channel_id, target_record.message_id).await {
+                    println!("Error deleting translated message: {:?}", why);
+                }
+                if let Err(why) = translation_store.delete(target_record.message_id).await {
+                    println!("Error removing translation record: {:?}", why);
+                }
+            }
+        }
+
+        if let Err(why) = translation_store.delete(deleted_message_id).await {
+            println!("Error removing translation record: {:?}", why);
+        }
+    }
+
+    async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
+
+        // The Discord outbound worker needs a Context to post through
+        // webhooks and write to the translation store, which isn't
+        // available until the client is ready. The receiver is only ever
+        // handed out once, so a second `ready` (e.g. a reconnect) won't
+        // spawn a duplicate worker.
+        let receiver_holder = {
+            let data = ctx.data.read().await;
+            data.get::<DiscordOutboundReceiver>().expect("Expected DiscordOutboundReceiver").clone()
+        };
+
+        if let Some(receiver) = receiver_holder.lock().await.take() {
+            let worker_ctx = ctx.clone();
+            tokio::spawn(async move {
+                run_discord_outbound_worker(worker_ctx, receiver).await;
+            });
+        }
     }
 }
 
-// Actually do the translation HTTP request to DeepL
-pub async fn translate_message (msg: String, language_code: String, api_key: &String) -> Translation {
+// The Discord delivery side of the bridge: receives translated messages
+// from the dispatcher and posts them through the destination channel's
+// webhook, then records the mapping so replies can be routed back.
+async fn run_discord_outbound_worker(ctx: Context, mut rx: mpsc::Receiver<OutboundMessage>) {
+    let translation_store = {
+        let data = ctx.data.read().await;
+        data.get::<Translations>().expect("Expected something").clone()
+    };
+
+    while let Some(outbound) = rx.recv().await {
+        let target_channel_id = match outbound.target.channel_id.parse::<u64>() {
+            Ok(id) => ChannelId::from(id),
+            Err(why) => {
+                println!("Error parsing bridge target channel id: {:?}", why);
+                continue;
+            }
+        };
 
-    // Construct the body of the request
-    let form_data = [("text", msg.clone()), ("target_lang", language_code.clone())];
+        let sent_message_result = post_translation(
+            &ctx,
+            target_channel_id,
+            &outbound.text,
+            &outbound.author_name,
+            &outbound.author_avatar_url,
+            &outbound.attachments,
+            &outbound.embeds
+        ).await;
 
-    // Do the response with some very ugly chaining until we get the result.
-    // TODO: Handle these errors gracefully.
-    let response = reqwest::Client::new()
-        .post(format!("https://api-free.deepl.com/v2/translate?auth_key={}", api_key)) // <- Create request builder
-        .header("User-Agent", "Actix-web")
-        .form(&form_data)
-        .send()
-        .await
-        .unwrap()
-        .text()
-        .await
-        .unwrap();
-
-    // DeepL gives us back a vector of possible translations, depending on the
-    // language that it thinks the message is written in. We only care about
-    // returning the first one.
-    println!("Posted message \"{}\" to DeepL with target language {} and got back {}", msg.clone(), language_code.clone(), &response.clone());
-    let translated_message: DeepLResponse = from_str(&response).unwrap();
-    let first_translation = translated_message.translations.first().unwrap();
-    if first_translation.detected_source_language == language_code.clone() {
-        return Translation { text: String::from(""), detected_source_language: language_code }
+        let sent_message = match sent_message_result {
+            Ok(sent_message) => sent_message,
+            Err(why) => {
+                println!("Error delivering bridge message: {:?}", why);
+                continue;
+            }
+        };
+
+        let source_channel_id = outbound.origin.channel_id.parse::<u64>().unwrap_or(0);
+        let source_message_id = outbound.origin.message_id.parse::<u64>().unwrap_or(0);
+        let author_id = outbound.author_id.parse::<u64>().unwrap_or(0);
+
+        let translation_record = TranslationRecord {
+            language: outbound.detected_source_language,
+            channel_id: ChannelId::from(source_channel_id),
+            message_id: sent_message.id,
+            source_message_id: MessageId::from(source_message_id),
+            is_bot_message: true,
+            posted_channel_id: target_channel_id,
+            author_id: UserId::from(author_id)
+        };
+
+        if let Err(why) = translation_store.insert(&translation_record).await {
+            println!("Error storing translation record: {:?}", why);
+        } else {
+            println!("Stored the message {:?} with key {}", translation_record, sent_message.id.clone());
+        }
+    }
+}
+
+
+// Applies a parsed config `Command` if the author has permission to change
+// the bot's config, persisting the change and updating the in-memory
+// runtime config that `Handler::message` reads on every message.
+async fn handle_command(ctx: &Context, msg: &Message, command: Command) {
+    // Unlike the channel-config commands below, `set language` changes a
+    // per-user preference, not shared server config, so it doesn't require
+    // manage-channels - anyone can set their own.
+    if let Command::SetLanguage { language } = command {
+        let language = language.to_uppercase();
+        if !is_supported_language(&language) {
+            println!("Rejecting unsupported language code \"{}\" from {} ({})", language, msg.author.id, msg.author.name);
+            return;
+        }
+
+        let language_manager = {
+            let data = ctx.data.read().await;
+            data.get::<LanguageManager>().expect("Expected LanguageManager").clone()
+        };
+
+        if let Err(why) = language_manager.set_language(msg.author.id, &language).await {
+            println!("Error storing language preference: {:?}", why);
+        }
+        return;
+    }
+
+    if !has_admin_permission(ctx, msg).await {
+        println!("Ignoring config command from {} ({}) - missing manage-channels permission", msg.author.id, msg.author.name);
+        return;
     }
-    first_translation.clone()
 
+    let config_store = {
+        let data = ctx.data.read().await;
+        data.get::<ConfigStore>().expect("Expected ConfigStore").clone()
+    };
+    let runtime_config = {
+        let data = ctx.data.read().await;
+        data.get::<RuntimeConfig>().expect("Expected RuntimeConfig").clone()
+    };
+
+    let result = match command {
+        Command::SetSource { channel_id, language } => {
+            let result = config_store.set_source(channel_id, &language).await;
+            if result.is_ok() {
+                runtime_config.write().await.source_channel_language.insert(channel_id, language);
+            }
+            result
+        },
+        Command::SetAggregate { channel_id } => {
+            let result = config_store.set_aggregate(channel_id).await;
+            if result.is_ok() {
+                runtime_config.write().await.aggregate_channel_id = channel_id;
+            }
+            result
+        },
+        Command::RemoveSource { channel_id } => {
+            let result = config_store.remove_source(channel_id).await;
+            if result.is_ok() {
+                runtime_config.write().await.source_channel_language.remove(&channel_id);
+            }
+            result
+        },
+        Command::Reset => {
+            let result = config_store.reset().await;
+            if result.is_ok() {
+                *runtime_config.write().await = ChannelConfig::default();
+            }
+            result
+        },
+        Command::SetLanguage { .. } => unreachable!("handled and returned above"),
+    };
+
+    if let Err(why) = result {
+        println!("Error applying config command: {:?}", why);
+        return;
+    }
+
+    // The Linkmap is derived from the channel config, so rebuild it whenever
+    // the config changes instead of trying to patch it in place.
+    let config = {
+        let data = ctx.data.read().await;
+        data.get::<AppConfig>().expect("something").clone()
+    };
+    let linkmap = {
+        let data = ctx.data.read().await;
+        data.get::<LinkmapKey>().expect("Expected LinkmapKey").clone()
+    };
+    let updated_channel_config = runtime_config.read().await.clone();
+    *linkmap.write().await = bridge::build_linkmap(&updated_channel_config, &config.default_language);
+}
+
+// Gate the config commands behind manage-channels, the same permission
+// Discord itself requires to edit channel settings.
+async fn has_admin_permission(ctx: &Context, msg: &Message) -> bool {
+    let member = match msg.member(&ctx).await {
+        Ok(member) => member,
+        Err(_) => return false,
+    };
+
+    match member.permissions(&ctx.cache).await {
+        Ok(permissions) => permissions.contains(Permissions::MANAGE_CHANNELS),
+        Err(_) => false,
+    }
 }
 
 fn is_bot_message(bot_id: UserId, message_author_id: UserId) -> bool {
     bot_id == message_author_id
 }
 
+// Discord's own `Attachment` carries a lot we don't need to forward
+// (content type, dimensions, proxy URL); the bridge only cares about where
+// to fetch the file from and what to call it.
+fn message_attachments(msg: &Message) -> Vec<AttachmentRef> {
+    msg.attachments
+        .iter()
+        .map(|attachment| AttachmentRef {
+            url: attachment.url.clone(),
+            filename: attachment.filename.clone(),
+        })
+        .collect()
+}
+
+// Discord embeds (link previews, rich `send_message` embeds) are otherwise
+// silently dropped by the bridge - carry the fields `EmbedRef` knows how to
+// reconstruct on the other side (see `webhook::post_translation`).
+fn message_embeds(msg: &Message) -> Vec<EmbedRef> {
+    msg.embeds
+        .iter()
+        .map(|embed| EmbedRef {
+            title: embed.title.clone(),
+            description: embed.description.clone(),
+            url: embed.url.clone(),
+            color: Some(embed.colour.0),
+            image_url: embed.image.as_ref().map(|image| image.url.clone()),
+            thumbnail_url: embed.thumbnail.as_ref().map(|thumbnail| thumbnail.url.clone()),
+        })
+        .collect()
+}
+
 fn is_monitored_channel(agg_channel_id: ChannelId, source_channel_list: HashMap<ChannelId, String>, msg_channel_id: ChannelId) -> bool {
     msg_channel_id == agg_channel_id || source_channel_list.contains_key(&msg_channel_id)
 }
@@ -380,13 +766,60 @@ async fn main() {
     let default_language = settings.get_str("default_language").unwrap();
     let aggregate_channel_id: u64 = settings.get("aggregate_channel_id").unwrap();
     let source_channel_language: HashMap<ChannelId, String> = settings.get("source_channel_language").unwrap();
+    let database_url = settings.get_str("database_url").unwrap();
 
     app_config.bot_token = bot_token.clone();
     app_config.bot_user_id = UserId::from(bot_user_id);
     app_config.deepl_api_key = deepl_api_key.clone();
     app_config.default_language = default_language.clone();
-    app_config.aggregate_channel_id = ChannelId::from(aggregate_channel_id);
-    app_config.source_channel_language = source_channel_language.clone();
+    app_config.database_url = database_url.clone();
+
+    let translation_store = TranslationStore::connect(&database_url)
+        .await
+        .expect("Failed to connect to the translation store database");
+
+    let config_store = ConfigStore::connect(translation_store.pool())
+        .await
+        .expect("Failed to connect to the config store database");
+
+    let language_manager = LanguageManager::connect(translation_store.pool())
+        .await
+        .expect("Failed to connect to the language preference database");
+
+    // `Settings` only seeds the channel config on a brand new database; once
+    // it's been seeded (or an admin command has touched it - including a
+    // `reset`), the database is the source of truth and Settings' values
+    // are ignored. We check `is_seeded` rather than whether `load()` came
+    // back empty, since an intentional `reset` leaves it empty on purpose.
+    let mut channel_config = config_store.load().await.expect("Failed to load channel config");
+    let already_seeded = config_store.is_seeded().await.expect("Failed to check channel config seed state");
+    if !already_seeded {
+        config_store
+            .set_aggregate(ChannelId::from(aggregate_channel_id))
+            .await
+            .expect("Failed to seed aggregate channel");
+        for (channel_id, language) in &source_channel_language {
+            config_store
+                .set_source(*channel_id, language)
+                .await
+                .expect("Failed to seed source channel");
+        }
+        channel_config = ChannelConfig {
+            aggregate_channel_id: ChannelId::from(aggregate_channel_id),
+            source_channel_language: source_channel_language.clone(),
+        };
+    }
+
+    let linkmap = bridge::build_linkmap(&channel_config, &app_config.default_language);
+
+    // Wire up the bridge: messages flow from the Discord handler into the
+    // dispatcher over `bridge_tx`, and from the dispatcher to the Discord
+    // delivery worker over `discord_outbound_tx`.
+    let (bridge_tx, bridge_rx) = mpsc::channel::<BridgeMessage>(100);
+    let (discord_outbound_tx, discord_outbound_rx) = mpsc::channel::<OutboundMessage>(100);
+
+    let linkmap = Arc::new(RwLock::new(linkmap));
+    bridge::spawn_dispatcher(bridge_rx, linkmap.clone(), app_config.deepl_api_key.clone(), discord_outbound_tx.clone());
 
     println!("App's config: {:?}", app_config);
 
@@ -406,11 +839,19 @@ async fn main() {
     {
         let mut data = discord_client.data.write().await;
 
-        // The Translation Value has the following type:
-        // Arc<RwLock<HashMap<MessageId, String>>>
-        // So, we have to insert the same type to it.
-        data.insert::<Translations>(Arc::new(RwLock::new(HashMap::default())));
+        // Nothing is loaded into memory at startup: the store reads and
+        // writes straight through to the database, so this is just wiring
+        // the connection pool into the data map.
+        data.insert::<Translations>(Arc::new(translation_store));
         data.insert::<AppConfig>(Arc::new(app_config));
+        data.insert::<WebhookCache>(Arc::new(RwLock::new(HashMap::default())));
+        data.insert::<ConfigStore>(Arc::new(config_store));
+        data.insert::<LanguageManager>(Arc::new(language_manager));
+        data.insert::<RuntimeConfig>(Arc::new(RwLock::new(channel_config)));
+        data.insert::<LinkmapKey>(linkmap);
+        data.insert::<BridgeInbound>(bridge_tx);
+        data.insert::<DiscordOutbound>(discord_outbound_tx);
+        data.insert::<DiscordOutboundReceiver>(Arc::new(tokio::sync::Mutex::new(Some(discord_outbound_rx))));
     }
 
     // Start listening for events by starting a single shard of Serenity
@@ -0,0 +1,207 @@
+/**
+
+    Stripping and restoring non-translatable spans.
+
+    DeepL doesn't know a Discord mention, a custom emoji token, or a code
+    block isn't prose - left alone it can mangle `<@123456789012345678>`
+    into something that no longer resolves to a user, or "translate" the
+    contents of a fenced code block. `translate_message` (deepl.rs) calls
+    `strip_non_translatable` before sending text to DeepL, then
+    `splice_non_translatable` on the result, so the DeepL request only ever
+    sees translatable prose and the spans it can't handle come back
+    untouched.
+
+*/
+
+// A span of the original message that shouldn't be sent to DeepL, and the
+// placeholder substituted for it in the stripped text.
+struct Span {
+    placeholder: String,
+    original: String,
+}
+
+pub struct StrippedText {
+    pub text: String,
+    spans: Vec<Span>,
+}
+
+// Replace fenced/inline code blocks, Discord mention/channel/role/emoji
+// tokens, and bare URLs with numbered placeholders, so DeepL only ever
+// translates ordinary prose.
+pub fn strip_non_translatable(input: &str) -> StrippedText {
+    let chars: Vec<char> = input.chars().collect();
+    let mut text = String::new();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let matched = match_fenced_code(&chars, i)
+            .or_else(|| match_inline_code(&chars, i))
+            .or_else(|| match_discord_token(&chars, i))
+            .or_else(|| match_url(&chars, i));
+
+        match matched {
+            Some(end) => {
+                let original: String = chars[i..end].iter().collect();
+                let placeholder = format!("{{{{BABELFISH{}}}}}", spans.len());
+                text.push_str(&placeholder);
+                spans.push(Span { placeholder, original });
+                i = end;
+            }
+            None => {
+                text.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+
+    StrippedText { text, spans }
+}
+
+// Put the original spans back wherever their placeholder survived the
+// round trip through DeepL. A placeholder DeepL mangled or dropped just
+// means that span is lost - there's no way to recover it from the
+// translated text alone.
+pub fn splice_non_translatable(translated: &str, stripped: &StrippedText) -> String {
+    let mut result = translated.to_string();
+    for span in &stripped.spans {
+        result = result.replace(&span.placeholder, &span.original);
+    }
+    result
+}
+
+fn match_fenced_code(chars: &[char], start: usize) -> Option<usize> {
+    if !starts_with(chars, start, "```") {
+        return None;
+    }
+    match find(chars, start + 3, "```") {
+        Some(close) => Some(close + 3),
+        None => Some(chars.len()),
+    }
+}
+
+fn match_inline_code(chars: &[char], start: usize) -> Option<usize> {
+    if chars.get(start) != Some(&'`') {
+        return None;
+    }
+    find(chars, start + 1, "`").map(|close| close + 1)
+}
+
+// Discord mentions (`<@id>`, `<@!id>`, `<@&id>`, `<#id>`) and custom emoji
+// (`<:name:id>`, `<a:name:id>`) all share the `<...>` shape DeepL has no
+// reason to know about.
+fn match_discord_token(chars: &[char], start: usize) -> Option<usize> {
+    if chars.get(start) != Some(&'<') {
+        return None;
+    }
+    let close = find(chars, start + 1, ">")?;
+    let token: String = chars[start + 1..close].iter().collect();
+    let looks_like_mention_or_emoji = token.starts_with('@')
+        || token.starts_with('#')
+        || token.starts_with(':')
+        || token.starts_with("a:");
+
+    if looks_like_mention_or_emoji {
+        Some(close + 1)
+    } else {
+        None
+    }
+}
+
+fn match_url(chars: &[char], start: usize) -> Option<usize> {
+    let prefix_len = if starts_with(chars, start, "https://") {
+        "https://".chars().count()
+    } else if starts_with(chars, start, "http://") {
+        "http://".chars().count()
+    } else {
+        return None;
+    };
+
+    let mut end = start + prefix_len;
+    while end < chars.len() && !chars[end].is_whitespace() {
+        end += 1;
+    }
+    Some(end)
+}
+
+fn starts_with(chars: &[char], start: usize, needle: &str) -> bool {
+    let needle: Vec<char> = needle.chars().collect();
+    start + needle.len() <= chars.len() && chars[start..start + needle.len()] == needle[..]
+}
+
+// The first index at or after `from` where `needle` occurs, if any.
+fn find(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.is_empty() {
+        return None;
+    }
+    (from..=chars.len().saturating_sub(needle.len()))
+        .find(|&i| chars[i..i + needle.len()] == needle[..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Splicing straight back in (without a translation in between) should
+    // reproduce the original text exactly - the baseline the DeepL round
+    // trip in `deepl.rs` depends on.
+    fn round_trip(input: &str) -> String {
+        let stripped = strip_non_translatable(input);
+        splice_non_translatable(&stripped.text, &stripped)
+    }
+
+    #[test]
+    fn leaves_plain_prose_untouched() {
+        let stripped = strip_non_translatable("just some ordinary text");
+        assert_eq!(stripped.text, "just some ordinary text");
+    }
+
+    #[test]
+    fn strips_fenced_code_blocks() {
+        let stripped = strip_non_translatable("before ```let x = 1;``` after");
+        assert_eq!(stripped.text, "before {{BABELFISH0}} after");
+        assert_eq!(round_trip("before ```let x = 1;``` after"), "before ```let x = 1;``` after");
+    }
+
+    #[test]
+    fn strips_inline_code() {
+        let stripped = strip_non_translatable("run `cargo test` now");
+        assert_eq!(stripped.text, "run {{BABELFISH0}} now");
+    }
+
+    #[test]
+    fn strips_discord_mentions_channels_roles_and_emoji() {
+        let input = "hey <@123>, check <#456> and <@&789> :wave: <:wave:111> <a:wave:222>";
+        let stripped = strip_non_translatable(input);
+        assert!(!stripped.text.contains("<@123>"));
+        assert!(!stripped.text.contains("<#456>"));
+        assert!(!stripped.text.contains("<@&789>"));
+        assert!(!stripped.text.contains("<:wave:111>"));
+        assert!(!stripped.text.contains("<a:wave:222>"));
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn strips_bare_urls_but_not_plain_angle_brackets() {
+        let input = "see https://example.com/path?x=1 <not-a-mention>";
+        let stripped = strip_non_translatable(input);
+        assert!(!stripped.text.contains("https://example.com/path?x=1"));
+        // `<not-a-mention>` doesn't look like a mention/emoji, so it's left
+        // as ordinary text DeepL is free to translate.
+        assert!(stripped.text.contains("<not-a-mention>"));
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn round_trips_a_message_with_several_spans() {
+        let input = "Hey <@123>, see `foo()` at https://example.com and ```fn bar() {}```";
+        assert_eq!(round_trip(input), input);
+    }
+
+    #[test]
+    fn splice_leaves_an_untouched_translation_unchanged() {
+        let stripped = strip_non_translatable("plain prose only");
+        assert_eq!(splice_non_translatable("texte simple seulement", &stripped), "texte simple seulement");
+    }
+}
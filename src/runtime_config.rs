@@ -0,0 +1,158 @@
+/**
+
+    The parts of the bot's configuration that can change at runtime.
+
+    `source_channel_language` and `aggregate_channel_id` used to live on the
+    immutable `AppConfig`, loaded once from `Settings` and never touched
+    again, so changing which channels were monitored meant editing a file
+    and restarting. They now live behind an `Arc<RwLock<ChannelConfig>>`,
+    mutated by the admin commands in `commands.rs`, and persisted to the
+    same database as the translation store so the changes survive a
+    restart.
+
+*/
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serenity::model::id::ChannelId;
+use serenity::prelude::{RwLock, TypeMapKey};
+use sqlx::sqlite::SqlitePool;
+use sqlx::Row;
+
+#[derive(Debug, Clone, Default)]
+pub struct ChannelConfig {
+    pub aggregate_channel_id: ChannelId,
+    pub source_channel_language: HashMap<ChannelId, String>,
+}
+
+pub struct RuntimeConfig;
+
+impl TypeMapKey for RuntimeConfig {
+    type Value = Arc<RwLock<ChannelConfig>>;
+}
+
+pub struct ConfigStore {
+    pool: SqlitePool,
+}
+
+impl TypeMapKey for ConfigStore {
+    type Value = Arc<ConfigStore>;
+}
+
+impl ConfigStore {
+    // Shares the translation store's connection pool rather than opening a
+    // second one, since it's the same database file.
+    pub async fn connect(pool: SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS channel_settings (
+                channel_id INTEGER PRIMARY KEY,
+                role TEXT NOT NULL,
+                language TEXT
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        // A separate table (rather than a row in `channel_settings`) so
+        // `reset`, which clears `channel_settings`, doesn't also erase the
+        // fact that the config has been initialized.
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS channel_settings_meta (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    // Whether the channel config has ever been touched - seeded from
+    // `Settings` on first run, or changed by an admin command since. `main`
+    // uses this instead of checking whether `load()` comes back empty, so
+    // that an intentional `reset` (which leaves the config empty on
+    // purpose) isn't mistaken for a never-initialized database and
+    // re-seeded from `Settings` on the next restart.
+    pub async fn is_seeded(&self) -> Result<bool, sqlx::Error> {
+        let row = sqlx::query("SELECT value FROM channel_settings_meta WHERE key = 'seeded'")
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    async fn mark_seeded(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT OR REPLACE INTO channel_settings_meta (key, value) VALUES ('seeded', '1')")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn load(&self) -> Result<ChannelConfig, sqlx::Error> {
+        let rows = sqlx::query("SELECT channel_id, role, language FROM channel_settings")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut config = ChannelConfig::default();
+        for row in rows {
+            let channel_id = ChannelId::from(row.get::<i64, _>("channel_id") as u64);
+            let role: String = row.get("role");
+
+            match role.as_str() {
+                "aggregate" => config.aggregate_channel_id = channel_id,
+                "source" => {
+                    let language: String = row.get("language");
+                    config.source_channel_language.insert(channel_id, language);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub async fn set_source(&self, channel_id: ChannelId, language: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO channel_settings (channel_id, role, language) VALUES (?, 'source', ?)",
+        )
+        .bind(channel_id.0 as i64)
+        .bind(language)
+        .execute(&self.pool)
+        .await?;
+
+        self.mark_seeded().await
+    }
+
+    pub async fn set_aggregate(&self, channel_id: ChannelId) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO channel_settings (channel_id, role, language) VALUES (?, 'aggregate', NULL)",
+        )
+        .bind(channel_id.0 as i64)
+        .execute(&self.pool)
+        .await?;
+
+        self.mark_seeded().await
+    }
+
+    pub async fn remove_source(&self, channel_id: ChannelId) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM channel_settings WHERE channel_id = ? AND role = 'source'")
+            .bind(channel_id.0 as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // A `reset` is itself an intentional change to the config, so it must
+    // mark the store as seeded too - otherwise a reset immediately followed
+    // by a restart would look just like a fresh database and get re-seeded
+    // from `Settings`.
+    pub async fn reset(&self) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM channel_settings").execute(&self.pool).await?;
+
+        self.mark_seeded().await
+    }
+}
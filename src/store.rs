@@ -0,0 +1,193 @@
+/**
+
+    Persistence for the translation memory.
+
+    `Handler::message` needs to map a message on one side of a translation
+    (source or aggregate) back to the message on the other side, so that
+    replies can be routed correctly. That used to live in an in-memory
+    `HashMap`, which meant every mapping was lost on restart. This module
+    keeps the same lookup shape but backs it with a SQLite database via
+    `sqlx`, so the mappings (and thus reply-routing) survive restarts.
+
+*/
+
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+
+// A single row of the `translations` table: a posted message, the source
+// message it was translated from, and the language of that source message
+// (what a reply to this message is translated back into). This is what
+// `PastTranslation` used to be, just persisted instead of held in memory.
+#[derive(Debug, Clone)]
+pub struct TranslationRecord {
+    pub message_id: MessageId,
+    // The channel to post a *reply* to this message into - the inverse
+    // side of the translation (source channel for a bot message, and vice
+    // versa).
+    pub channel_id: ChannelId,
+    pub source_message_id: MessageId,
+    pub language: String,
+    pub is_bot_message: bool,
+    // The channel this specific message actually lives in.
+    pub posted_channel_id: ChannelId,
+    // The human who wrote this message's content, even for a record that
+    // represents the bot's translated repost of their words. Lets a later
+    // reply look up their `language.rs` preference instead of just falling
+    // back to the channel's configured language - see `Handler::message`'s
+    // reply branch.
+    pub author_id: UserId,
+}
+
+fn row_to_record(row: SqliteRow) -> TranslationRecord {
+    TranslationRecord {
+        message_id: MessageId::from(row.get::<i64, _>("message_id") as u64),
+        channel_id: ChannelId::from(row.get::<i64, _>("channel_id") as u64),
+        source_message_id: MessageId::from(row.get::<i64, _>("source_message_id") as u64),
+        language: row.get("language"),
+        is_bot_message: row.get::<i64, _>("is_bot_message") != 0,
+        posted_channel_id: ChannelId::from(row.get::<i64, _>("posted_channel_id") as u64),
+        author_id: UserId::from(row.get::<i64, _>("author_id") as u64),
+    }
+}
+
+pub struct TranslationStore {
+    pool: SqlitePool,
+}
+
+impl TranslationStore {
+    // Gives out a clone of the underlying connection pool so other stores
+    // (e.g. `runtime_config::ConfigStore`) can share the same database file
+    // instead of opening a second connection.
+    pub fn pool(&self) -> SqlitePool {
+        self.pool.clone()
+    }
+
+    // Opens (and, if necessary, creates) the database at `database_url` and
+    // makes sure the `translations` table exists. We run this as a plain
+    // `CREATE TABLE IF NOT EXISTS` rather than a migrations directory, since
+    // the schema is only a handful of tables, each created the same way by
+    // the store that owns it (`ConfigStore`, `LanguageManager`).
+    //
+    // `SqlitePoolOptions::connect` alone won't create a missing database
+    // file, so on a brand new `database_url` the very first run would fail;
+    // `create_if_missing` makes a fresh deployment work without requiring
+    // the file (or a `?mode=rwc` suffix) to exist beforehand.
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let connect_options = database_url
+            .parse::<SqliteConnectOptions>()?
+            .create_if_missing(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(connect_options)
+            .await?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS translations (
+                message_id INTEGER PRIMARY KEY,
+                channel_id INTEGER NOT NULL,
+                source_message_id INTEGER NOT NULL,
+                language TEXT NOT NULL,
+                is_bot_message INTEGER NOT NULL,
+                posted_channel_id INTEGER NOT NULL,
+                author_id INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await?;
+
+        // `message_id` is already the primary key, which gives it a unique
+        // index for free - an explicit index there would just be a
+        // duplicate. `source_message_id` is the column `get_by_source_
+        // message_id` below actually filters on without one, so that's the
+        // one worth indexing.
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_translations_source_message_id ON translations (source_message_id)",
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self { pool })
+    }
+
+    // Look up a message we've seen before, either a source message or one
+    // the bot posted, so replies can be routed to the other side.
+    pub async fn get(&self, message_id: MessageId) -> Option<TranslationRecord> {
+        let row = sqlx::query(
+            "SELECT message_id, channel_id, source_message_id, language, is_bot_message, posted_channel_id, author_id
+             FROM translations WHERE message_id = ?",
+        )
+        .bind(message_id.0 as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()??;
+
+        Some(row_to_record(row))
+    }
+
+    // Look up the other side of a translation by the source message id,
+    // e.g. "find the aggregate bot message posted for this source message"
+    // when propagating an edit or delete.
+    pub async fn get_by_source_message_id(
+        &self,
+        source_message_id: MessageId,
+        is_bot_message: bool,
+    ) -> Option<TranslationRecord> {
+        let row = sqlx::query(
+            "SELECT message_id, channel_id, source_message_id, language, is_bot_message, posted_channel_id, author_id
+             FROM translations WHERE source_message_id = ? AND is_bot_message = ?",
+        )
+        .bind(source_message_id.0 as i64)
+        .bind(is_bot_message as i64)
+        .fetch_optional(&self.pool)
+        .await
+        .ok()??;
+
+        Some(row_to_record(row))
+    }
+
+    // Remember a message so a later reply (in either channel) can be routed
+    // back to the other side of the translation.
+    pub async fn insert(&self, record: &TranslationRecord) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO translations
+                (message_id, channel_id, source_message_id, language, is_bot_message, posted_channel_id, author_id, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, strftime('%s', 'now'))",
+        )
+        .bind(record.message_id.0 as i64)
+        .bind(record.channel_id.0 as i64)
+        .bind(record.source_message_id.0 as i64)
+        .bind(&record.language)
+        .bind(record.is_bot_message as i64)
+        .bind(record.posted_channel_id.0 as i64)
+        .bind(record.author_id.0 as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Forget a message, e.g. once it (or its translation) has been deleted.
+    pub async fn delete(&self, message_id: MessageId) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM translations WHERE message_id = ?")
+            .bind(message_id.0 as i64)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // Drop rows older than `days` so the table doesn't grow unbounded. This
+    // isn't called automatically anywhere yet; it's here so `main` (or a
+    // future scheduled task) can call it on a timer.
+    pub async fn prune_older_than_days(&self, days: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM translations WHERE created_at < strftime('%s', 'now') - (? * 86400)")
+            .bind(days)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+/**
+
+    Posting translations through per-channel webhooks.
+
+    Rather than appending "(from: {name})" to the translated text, we post
+    through a Discord webhook with its username and avatar set to the
+    source author's, the same impersonation technique PluralKit and
+    reminder-bot rely on for `WEBHOOK_AVATAR`-style forwarding. Webhooks are
+    created on demand per channel and cached so we don't spam the Discord
+    API re-creating them on every message.
+
+    Webhook messages can't natively reply to another message, so reply
+    threading still goes through the `Translations` store (see `store.rs`);
+    this module only concerns itself with getting the translated text onto
+    the channel looking like the original author said it - including any
+    embeds the source message carried, forwarded via `ExecuteWebhook::
+    set_embeds` alongside the content.
+
+*/
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serenity::http::AttachmentType;
+use serenity::model::channel::{Embed, Message};
+use serenity::model::id::ChannelId;
+use serenity::model::webhook::Webhook;
+use serenity::prelude::{Context, RwLock, TypeMapKey};
+
+use crate::bridge::{AttachmentRef, EmbedRef};
+
+// The name we give webhooks we create, so that on restart we can find the
+// one we made last time instead of creating a new one every time.
+const WEBHOOK_NAME: &str = "babelfish";
+
+pub struct WebhookCache;
+
+impl TypeMapKey for WebhookCache {
+    type Value = Arc<RwLock<HashMap<ChannelId, Webhook>>>;
+}
+
+// Find (or create) the webhook we post translations through in a channel,
+// checking the in-memory cache first.
+pub(crate) async fn get_or_create_webhook(ctx: &Context, channel_id: ChannelId) -> serenity::Result<Webhook> {
+    let cache = {
+        let data = ctx.data.read().await;
+        data.get::<WebhookCache>().expect("Expected WebhookCache").clone()
+    };
+
+    if let Some(webhook) = cache.read().await.get(&channel_id) {
+        return Ok(webhook.clone());
+    }
+
+    let existing_webhooks = channel_id.webhooks(&ctx.http).await?;
+    let webhook = match existing_webhooks
+        .into_iter()
+        .find(|webhook| webhook.name.as_deref() == Some(WEBHOOK_NAME))
+    {
+        Some(webhook) => webhook,
+        None => channel_id.create_webhook(&ctx.http, WEBHOOK_NAME).await?,
+    };
+
+    cache.write().await.insert(channel_id, webhook.clone());
+
+    Ok(webhook)
+}
+
+// Webhooks can't just link an attachment from its original URL the way a
+// normal reply could - Discord only serves the original message's
+// attachments to participants of the channel it lives in, and won't still
+// have them once that message is gone. So we download the bytes ourselves
+// and re-upload them alongside the translation.
+async fn download_attachment(attachment: &AttachmentRef) -> Option<Vec<u8>> {
+    match reqwest::get(&attachment.url).await {
+        Ok(response) => match response.bytes().await {
+            Ok(bytes) => Some(bytes.to_vec()),
+            Err(why) => {
+                println!("Error reading attachment body {}: {:?}", attachment.url, why);
+                None
+            }
+        },
+        Err(why) => {
+            println!("Error downloading attachment {}: {:?}", attachment.url, why);
+            None
+        }
+    }
+}
+
+// Unlike attachments, embeds carry no bytes to re-host - they're just
+// structured fields DeepL never saw, so there's nothing to translate or
+// download here, only to rebuild as a serenity `Embed` the webhook builder
+// can accept.
+fn build_embed(embed: &EmbedRef) -> Embed {
+    Embed::fake(|e| {
+        if let Some(title) = &embed.title {
+            e.title(title);
+        }
+        if let Some(description) = &embed.description {
+            e.description(description);
+        }
+        if let Some(url) = &embed.url {
+            e.url(url);
+        }
+        if let Some(color) = embed.color {
+            e.color(color);
+        }
+        if let Some(image_url) = &embed.image_url {
+            e.image(image_url);
+        }
+        if let Some(thumbnail_url) = &embed.thumbnail_url {
+            e.thumbnail(thumbnail_url);
+        }
+        e
+    })
+}
+
+// Post a translated message into `channel_id`, impersonating the original
+// author via the channel's webhook. Attachments that fail to download are
+// dropped rather than failing the whole message.
+pub async fn post_translation(
+    ctx: &Context,
+    channel_id: ChannelId,
+    text: &str,
+    author_name: &str,
+    author_avatar_url: &str,
+    attachments: &[AttachmentRef],
+    embeds: &[EmbedRef],
+) -> serenity::Result<Message> {
+    let webhook = get_or_create_webhook(ctx, channel_id).await?;
+
+    let mut files = Vec::new();
+    for attachment in attachments {
+        if let Some(data) = download_attachment(attachment).await {
+            files.push(AttachmentType::Bytes { data: Cow::from(data), filename: attachment.filename.clone() });
+        }
+    }
+
+    let built_embeds: Vec<Embed> = embeds.iter().map(build_embed).collect();
+
+    let sent_message = webhook
+        .execute(&ctx.http, true, |w| {
+            w.content(text).username(author_name).avatar_url(author_avatar_url).add_files(files).set_embeds(built_embeds)
+        })
+        .await?;
+
+    Ok(sent_message.expect("wait was true, so the sent message is always returned"))
+}
+
+// Edit a previously-posted translation in place, e.g. when the source
+// message it came from is edited.
+pub async fn edit_translation(
+    ctx: &Context,
+    channel_id: ChannelId,
+    message_id: serenity::model::id::MessageId,
+    text: &str,
+) -> serenity::Result<Message> {
+    let webhook = get_or_create_webhook(ctx, channel_id).await?;
+
+    webhook.edit_message(&ctx.http, message_id, |m| m.content(text)).await
+}
+
+// Delete a previously-posted translation, e.g. when the source message it
+// came from is deleted.
+pub async fn delete_translation(
+    ctx: &Context,
+    channel_id: ChannelId,
+    message_id: serenity::model::id::MessageId,
+) -> serenity::Result<()> {
+    let webhook = get_or_create_webhook(ctx, channel_id).await?;
+
+    webhook.delete_message(&ctx.http, message_id).await
+}